@@ -10,15 +10,18 @@
 //! information on how to do that.
 
 use std::{
+    cell::RefCell,
+    collections::VecDeque,
     convert::TryInto,
     ffi::CString,
-    io::{Seek, SeekFrom, Write},
+    io::{self, Seek, SeekFrom, Write},
     marker::PhantomData,
+    rc::Rc,
 };
 
 pub use cookie_factory::GenError;
 use cookie_factory::{
-    bytes::{ne_u32, ne_u8},
+    bytes::{ne_i64, ne_u32, ne_u8},
     combinator::slice,
     gen,
     multi::all,
@@ -28,6 +31,10 @@ use cookie_factory::{
 
 use super::{CanonicalFixedSizedPod, FixedSizedPod};
 
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod slice_writer;
+
 /// Implementors of this trait are able to serialize themselves into a SPA pod by using a [`PodSerializer`].
 ///
 /// Their [`serialize`](`PodSerialize::serialize`) method should invoke exactly one of the `serialize_*()` methods
@@ -148,6 +155,94 @@ impl<P: FixedSizedPod> PodSerialize for [P] {
     }
 }
 
+/// One entry in the size tree built up by the counting pass of [`serialize_to_writer`].
+///
+/// Stores the final body size of a single compound pod (`Struct`, `Object`, `Choice` or
+/// `Sequence`),
+/// plus the sizes of any compound pods opened directly inside its body, in the order they
+/// were opened.
+#[derive(Debug, Default)]
+struct SizeNode {
+    size: u64,
+    children: VecDeque<SizeNode>,
+}
+
+/// Tracks the compound pods currently being serialized by a [`PodSerializer`], so their sizes
+/// can be recorded (first pass) or replayed (second pass) by
+/// [`serialize_to_writer`].
+///
+/// Each stack frame holds the children recorded or still to be replayed for whichever
+/// compound pod is currently open at that depth.
+#[derive(Clone)]
+enum SizeTracker {
+    /// First pass: every compound pod appends its finished [`SizeNode`] to the innermost open
+    /// frame once it is finished.
+    Count(Rc<RefCell<Vec<VecDeque<SizeNode>>>>),
+    /// Second pass: every compound pod consumes its already-known [`SizeNode`] from the
+    /// innermost open frame as soon as it begins.
+    Replay(Rc<RefCell<Vec<VecDeque<SizeNode>>>>),
+}
+
+/// A [`Write`] + [`Seek`] sink used by the counting pass of [`serialize_to_writer`].
+///
+/// Discards every byte written to it, but keeps track of the furthest position ever written
+/// to, which after a full, well-formed serialization equals the total number of bytes that
+/// would have been written to a real writer.
+#[derive(Default)]
+struct ByteCounter {
+    pos: u64,
+    len: u64,
+}
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pos += buf.len() as u64;
+        self.len = self.len.max(self.pos);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ByteCounter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = match pos {
+            SeekFrom::Start(n) => n,
+            SeekFrom::Current(n) => (self.pos as i64 + n) as u64,
+            SeekFrom::End(n) => (self.len as i64 + n) as u64,
+        };
+        Ok(self.pos)
+    }
+}
+
+/// Adapts a plain [`Write`]r that doesn't implement [`Seek`] so it can be used as the `O`
+/// parameter of [`PodSerializer`] during the replay pass of
+/// [`serialize_to_writer`].
+///
+/// `seek` is never actually called while replaying, because every compound pod's header size
+/// is already known up front; its implementation only exists to satisfy the `O: Seek` bound.
+struct NonSeekingWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> Write for NonSeekingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Seek for NonSeekingWriter<W> {
+    fn seek(&mut self, _pos: SeekFrom) -> io::Result<u64> {
+        unreachable!("serialize_to_writer never seeks during its replay pass")
+    }
+}
+
 /// This struct is returned by [`PodSerialize`] implementors on serialization sucess.
 ///
 /// Because this can only be constructed by the [`PodSerializer`], [`PodSerialize`] implementors are forced
@@ -168,6 +263,62 @@ pub struct PodSerializer<O: Write + Seek> {
     /// and must then put the writer back inside.
     /// The [`Self::gen`] function can be used to do this.
     out: Option<O>,
+    /// `Some` while this serializer is taking part in a [`serialize_to_writer`] call,
+    /// in which case every compound pod must record or replay its size through it instead of
+    /// always seeking back to patch its header. `None` for ordinary, directly-seekable output.
+    size_tracker: Option<SizeTracker>,
+}
+
+/// Serialize the provided POD into `out`, without requiring `out` to implement [`Seek`].
+///
+/// [`PodSerializer::serialize_struct`], [`PodSerializer::serialize_object`],
+/// [`PodSerializer::serialize_choice`] and [`PodSerializer::serialize_sequence`] normally reserve their header with a placeholder size
+/// and seek back to patch it in once their body has been written, which makes it impossible
+/// to serialize straight into a pipe, socket, or other write-only stream. This instead runs
+/// the [`PodSerialize`] implementation once against a sink that only counts bytes, to
+/// discover every such header's final size ahead of time, then runs it again against `out`,
+/// writing each header's size inline instead of seeking back to patch it.
+///
+/// Because the implementation is driven twice, it must not have side effects beyond writing
+/// to the provided serializer.
+pub fn serialize_to_writer<W, P>(out: W, pod: &P) -> Result<(W, u64), GenError>
+where
+    W: Write,
+    P: PodSerialize + ?Sized,
+{
+    // First pass: drive the real serialization logic against a sink that discards its
+    // bytes but still counts them, recording the size of every compound pod (and the
+    // compound pods nested inside it) as it is finished.
+    let root = Rc::new(RefCell::new(vec![VecDeque::new()]));
+    let counter = PodSerializer {
+        out: Some(ByteCounter::default()),
+        size_tracker: Some(SizeTracker::Count(Rc::clone(&root))),
+    };
+    let total_len = pod.serialize(counter)?.len;
+    let root_children = Rc::try_unwrap(root)
+        .unwrap_or_else(|_| panic!("counting pass left dangling references to its size tree"))
+        .into_inner()
+        .pop()
+        .expect("counting pass did not leave behind a root frame");
+
+    // Second pass: replay the same serialization, writing each compound pod's now-known
+    // size into its header up front instead of seeking back to patch it.
+    let replaying = PodSerializer {
+        out: Some(NonSeekingWriter { inner: out }),
+        size_tracker: Some(SizeTracker::Replay(Rc::new(RefCell::new(vec![
+            root_children,
+        ])))),
+    };
+    let success = pod.serialize(replaying)?;
+
+    Ok((
+        success
+            .serializer
+            .out
+            .expect("Serializer does not contain a writer")
+            .inner,
+        total_len,
+    ))
 }
 
 impl<O: Write + Seek> PodSerializer<O> {
@@ -182,7 +333,10 @@ impl<O: Write + Seek> PodSerializer<O> {
     where
         P: PodSerialize + ?Sized,
     {
-        let serializer = Self { out: Some(out) };
+        let serializer = Self {
+            out: Some(out),
+            size_tracker: None,
+        };
 
         pod.serialize(serializer).map(|success| {
             (
@@ -195,6 +349,62 @@ impl<O: Write + Seek> PodSerializer<O> {
         })
     }
 
+    /// Called when beginning to serialize a compound pod (`Struct`, `Object`, `Choice` or
+    /// `Sequence`).
+    ///
+    /// Returns `Some(size)` with the already-known total body size when
+    /// [`serialize_to_writer`] is replaying a previous counting pass, in which case the
+    /// header must be written immediately with that size instead of a placeholder. Returns
+    /// `None` otherwise, in which case the caller must reserve a placeholder header and patch
+    /// it once the body is known, as usual.
+    fn compound_begin(&mut self) -> Option<u64> {
+        match &self.size_tracker {
+            Some(SizeTracker::Count(stack)) => {
+                stack.borrow_mut().push(VecDeque::new());
+                None
+            }
+            Some(SizeTracker::Replay(stack)) => {
+                let node = stack
+                    .borrow_mut()
+                    .last_mut()
+                    .expect("no open frame to read a replayed pod size from")
+                    .pop_front()
+                    .expect("counting pass did not record a size for this pod");
+                let size = node.size;
+                stack.borrow_mut().push(node.children);
+                Some(size)
+            }
+            None => None,
+        }
+    }
+
+    /// Called when finishing serialization of a compound pod, with its final body `size`.
+    ///
+    /// Mirror image of [`Self::compound_begin`]: records `size` into the enclosing frame while
+    /// counting, or simply discards the (now fully consumed) frame opened for it while
+    /// replaying.
+    fn compound_end(&mut self, size: u64) {
+        let tracker = match &self.size_tracker {
+            Some(tracker) => tracker.clone(),
+            None => return,
+        };
+
+        let stack = match &tracker {
+            SizeTracker::Count(stack) => stack,
+            SizeTracker::Replay(stack) => stack,
+        };
+
+        let children = stack.borrow_mut().pop().expect("no open frame to close");
+
+        if let SizeTracker::Count(_) = tracker {
+            stack
+                .borrow_mut()
+                .last_mut()
+                .expect("no parent frame to record this pod's size into")
+                .push_back(SizeNode { size, children });
+        }
+    }
+
     /// Helper serialization method for serializing the Pod header.
     ///
     /// # Parameters
@@ -277,6 +487,42 @@ impl<O: Write + Seek> PodSerializer<O> {
         self.write_pod(bytes.len(), spa_sys::SPA_TYPE_Bytes, slice(bytes))
     }
 
+    /// Serialize an `Id` pod.
+    pub fn serialize_id(self, id: u32) -> Result<SerializeSuccess<O>, GenError> {
+        self.write_pod(4, spa_sys::SPA_TYPE_Id, ne_u32(id))
+    }
+
+    /// Serialize an `Fd` pod.
+    pub fn serialize_fd(self, fd: i64) -> Result<SerializeSuccess<O>, GenError> {
+        self.write_pod(8, spa_sys::SPA_TYPE_Fd, ne_i64(fd))
+    }
+
+    /// Serialize a `Rectangle` pod.
+    pub fn serialize_rectangle(
+        self,
+        width: u32,
+        height: u32,
+    ) -> Result<SerializeSuccess<O>, GenError> {
+        self.write_pod(
+            8,
+            spa_sys::SPA_TYPE_Rectangle,
+            pair(ne_u32(width), ne_u32(height)),
+        )
+    }
+
+    /// Serialize a `Fraction` pod.
+    pub fn serialize_fraction(
+        self,
+        num: u32,
+        denom: u32,
+    ) -> Result<SerializeSuccess<O>, GenError> {
+        self.write_pod(
+            8,
+            spa_sys::SPA_TYPE_Fraction,
+            pair(ne_u32(num), ne_u32(denom)),
+        )
+    }
+
     /// Begin serializing an `Array` pod with exactly `length` elements.
     pub fn serialize_array<P: FixedSizedPod>(
         mut self,
@@ -300,21 +546,142 @@ impl<O: Write + Seek> PodSerializer<O> {
 
     /// Begin serializing a `Struct` pod.
     pub fn serialize_struct(mut self) -> Result<StructPodSerializer<O>, GenError> {
-        let header_position = self
-            .out
-            .as_mut()
-            .expect("PodSerializer does not contain a writer")
-            // This does not actually change the writer, only returns the current position.
-            .seek(SeekFrom::Current(0))
-            .expect("Could not get current position in writer");
+        let known_size = self.compound_begin();
 
-        // Write a size of 0 for now, this will be updated when calling `StructPodSerializer.end()`.
-        self.gen(Self::header(0, spa_sys::SPA_TYPE_Struct))?;
+        let header_position = if let Some(size) = known_size {
+            self.gen(Self::header(size as usize, spa_sys::SPA_TYPE_Struct))?;
+            0
+        } else {
+            let pos = self
+                .out
+                .as_mut()
+                .expect("PodSerializer does not contain a writer")
+                // This does not actually change the writer, only returns the current position.
+                .seek(SeekFrom::Current(0))
+                .expect("Could not get current position in writer");
+
+            // Write a size of 0 for now, this will be updated when calling `StructPodSerializer.end()`.
+            self.gen(Self::header(0, spa_sys::SPA_TYPE_Struct))?;
+            pos
+        };
 
         Ok(StructPodSerializer {
             serializer: Some(self),
             header_position,
             written: 0,
+            known_size,
+        })
+    }
+
+    /// Begin serializing an `Object` pod of the given `object_type` (e.g.
+    /// `spa_sys::SPA_TYPE_OBJECT_Props`) and `object_id`.
+    pub fn serialize_object(
+        mut self,
+        object_type: u32,
+        object_id: u32,
+    ) -> Result<ObjectPodSerializer<O>, GenError> {
+        let known_size = self.compound_begin();
+
+        let header_position = if let Some(size) = known_size {
+            self.gen(Self::header(size as usize, spa_sys::SPA_TYPE_Object))?;
+            0
+        } else {
+            let pos = self
+                .out
+                .as_mut()
+                .expect("PodSerializer does not contain a writer")
+                // This does not actually change the writer, only returns the current position.
+                .seek(SeekFrom::Current(0))
+                .expect("Could not get current position in writer");
+
+            // Write a size of 0 for now, this will be updated when calling `ObjectPodSerializer.end()`.
+            self.gen(Self::header(0, spa_sys::SPA_TYPE_Object))?;
+            pos
+        };
+
+        let written = self.gen(pair(ne_u32(object_type), ne_u32(object_id)))? as usize;
+
+        Ok(ObjectPodSerializer {
+            serializer: Some(self),
+            header_position,
+            written,
+            known_size,
+        })
+    }
+
+    /// Begin serializing a `Choice` pod of the given `choice_type` (one of
+    /// `spa_sys::SPA_CHOICE_*`) and `flags`.
+    ///
+    /// The choice's values will be serialized into the [`FixedSizedPod`] type `P`.
+    pub fn serialize_choice<P: FixedSizedPod>(
+        mut self,
+        choice_type: u32,
+        flags: u32,
+    ) -> Result<ChoicePodSerializer<O, P>, GenError> {
+        let known_size = self.compound_begin();
+
+        let header_position = if let Some(size) = known_size {
+            self.gen(Self::header(size as usize, spa_sys::SPA_TYPE_Choice))?;
+            0
+        } else {
+            let pos = self
+                .out
+                .as_mut()
+                .expect("PodSerializer does not contain a writer")
+                // This does not actually change the writer, only returns the current position.
+                .seek(SeekFrom::Current(0))
+                .expect("Could not get current position in writer");
+
+            // Write a size of 0 for now, this will be updated when calling `ChoicePodSerializer.end()`.
+            self.gen(Self::header(0, spa_sys::SPA_TYPE_Choice))?;
+            pos
+        };
+
+        self.gen(tuple((
+            pair(ne_u32(choice_type), ne_u32(flags)),
+            Self::header(P::CanonicalType::SIZE as usize, P::CanonicalType::TYPE),
+        )))?;
+
+        Ok(ChoicePodSerializer {
+            serializer: self,
+            header_position,
+            choice_type,
+            written: 0,
+            known_size,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Begin serializing a `Sequence` pod carrying time-ordered control events, with a time
+    /// `unit` of 0 meaning the offsets in [`SequencePodSerializer::serialize_control`] are
+    /// raw sample counts.
+    pub fn serialize_sequence(mut self, unit: u32) -> Result<SequencePodSerializer<O>, GenError> {
+        let known_size = self.compound_begin();
+
+        let header_position = if let Some(size) = known_size {
+            self.gen(Self::header(size as usize, spa_sys::SPA_TYPE_Sequence))?;
+            0
+        } else {
+            let pos = self
+                .out
+                .as_mut()
+                .expect("PodSerializer does not contain a writer")
+                // This does not actually change the writer, only returns the current position.
+                .seek(SeekFrom::Current(0))
+                .expect("Could not get current position in writer");
+
+            // Write a size of 0 for now, this will be updated when calling `SequencePodSerializer.end()`.
+            self.gen(Self::header(0, spa_sys::SPA_TYPE_Sequence))?;
+            pos
+        };
+
+        let written = self.gen(pair(ne_u32(unit), ne_u32(0)))? as usize;
+
+        Ok(SequencePodSerializer {
+            serializer: Some(self),
+            header_position,
+            written,
+            known_size,
         })
     }
 }
@@ -395,6 +762,10 @@ pub struct StructPodSerializer<O: Write + Seek> {
     /// The position to seek to when modifying header.
     header_position: u64,
     written: usize,
+    /// `Some` if this struct's header was already written with its final size up front, as
+    /// part of [`serialize_to_writer`]'s replay pass, in which case `end()`
+    /// must not seek back to patch it.
+    known_size: Option<u64>,
 }
 
 impl<O: Write + Seek> StructPodSerializer<O> {
@@ -421,28 +792,32 @@ impl<O: Write + Seek> StructPodSerializer<O> {
             .serializer
             .expect("StructSerializer does not contain a serializer");
 
-        // Seek to header position, write header with updates size, seek back.
-        serializer
-            .out
-            .as_mut()
-            .expect("Serializer does not contain a writer")
-            .seek(SeekFrom::Start(self.header_position))
-            .expect("Failed to seek to header position");
+        if self.known_size.is_none() {
+            // Seek to header position, write header with updates size, seek back.
+            serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::Start(self.header_position))
+                .expect("Failed to seek to header position");
 
-        serializer.gen(PodSerializer::header(
-            self.written,
-            spa_sys::SPA_TYPE_Struct,
-        ))?;
+            serializer.gen(PodSerializer::header(
+                self.written,
+                spa_sys::SPA_TYPE_Struct,
+            ))?;
 
-        serializer
-            .out
-            .as_mut()
-            .expect("Serializer does not contain a writer")
-            .seek(SeekFrom::End(0))
-            .expect("Failed to seek to end");
+            serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::End(0))
+                .expect("Failed to seek to end");
+        }
 
         // No padding needed: Last field will already end aligned.
 
+        serializer.compound_end(self.written as u64);
+
         // Return full length of written pod.
         Ok(SerializeSuccess {
             serializer,
@@ -450,3 +825,515 @@ impl<O: Write + Seek> StructPodSerializer<O> {
         })
     }
 }
+
+/// This struct handles serializing `Object` pods.
+///
+/// It can be obtained by calling [`PodSerializer::serialize_object`].
+///
+/// Its [`serialize_property`](`Self::serialize_property`) method can be repeatedly called to
+/// serialize one property each. To finalize the object, its [`end`](`Self::end`) method must
+/// be called.
+pub struct ObjectPodSerializer<O: Write + Seek> {
+    /// The serializer is saved in an option, but can be expected to always be a `Some`
+    /// when `serialize_property()` or `end()` is called.
+    serializer: Option<PodSerializer<O>>,
+    /// The position to seek to when modifying header.
+    header_position: u64,
+    /// Bytes written to the object body so far. Starts at 8 to account for the
+    /// `object_type`/`object_id` pair written by `serialize_object`.
+    written: usize,
+    /// `Some` if this object's header was already written with its final size up front, as
+    /// part of [`serialize_to_writer`]'s replay pass, in which case `end()`
+    /// must not seek back to patch it.
+    known_size: Option<u64>,
+}
+
+impl<O: Write + Seek> ObjectPodSerializer<O> {
+    /// Serialize a single property of the object.
+    ///
+    /// Returns the amount of bytes written for this property.
+    pub fn serialize_property<P>(
+        &mut self,
+        key: u32,
+        flags: u32,
+        value: &P,
+    ) -> Result<u64, GenError>
+    where
+        P: PodSerialize + ?Sized,
+    {
+        let header_len = self
+            .serializer
+            .as_mut()
+            .expect("ObjectPodSerializer does not contain a serializer")
+            .gen(pair(ne_u32(key), ne_u32(flags)))?;
+
+        let success = value.serialize(
+            self.serializer
+                .take()
+                .expect("ObjectPodSerializer does not contain a serializer"),
+        )?;
+
+        self.written += header_len as usize + success.len as usize;
+        self.serializer = Some(success.serializer);
+        Ok(header_len + success.len)
+    }
+
+    /// Finish serialization of the pod.
+    pub fn end(self) -> Result<SerializeSuccess<O>, GenError> {
+        let mut serializer = self
+            .serializer
+            .expect("ObjectPodSerializer does not contain a serializer");
+
+        if self.known_size.is_none() {
+            // Seek to header position, write header with updated size, seek back.
+            serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::Start(self.header_position))
+                .expect("Failed to seek to header position");
+
+            serializer.gen(PodSerializer::header(self.written, spa_sys::SPA_TYPE_Object))?;
+
+            serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::End(0))
+                .expect("Failed to seek to end");
+        }
+
+        // No padding needed: properties always end 8-byte aligned, as does the
+        // object_type/object_id pair at the start of the body.
+
+        serializer.compound_end(self.written as u64);
+
+        Ok(SerializeSuccess {
+            serializer,
+            len: self.written as u64 + 8,
+        })
+    }
+}
+
+/// This struct handles serializing `Choice` pods.
+///
+/// It can be obtained by calling [`PodSerializer::serialize_choice`].
+///
+/// Its [`serialize_value`](`Self::serialize_value`) method can be repeatedly called to
+/// serialize one value each. To finalize the choice, its [`end`](`Self::end`) method must be
+/// called, which will check that the number of values serialized is valid for the choice type
+/// that was specified in [`PodSerializer::serialize_choice`].
+pub struct ChoicePodSerializer<O: Write + Seek, P: FixedSizedPod> {
+    serializer: PodSerializer<O>,
+    /// The position to seek to when modifying header.
+    header_position: u64,
+    /// One of `spa_sys::SPA_CHOICE_*`, used by `end()` to check the number of values is valid.
+    choice_type: u32,
+    /// The number of values that have been written already.
+    written: u32,
+    /// `Some` if this choice's header was already written with its final size up front, as
+    /// part of [`serialize_to_writer`]'s replay pass, in which case `end()`
+    /// must not seek back to patch it.
+    known_size: Option<u64>,
+    /// The struct has the type parameter P to ensure all serialized values are the same type,
+    /// but doesn't actually own any P, so we need the `PhantomData<P>` instead.
+    _phantom: PhantomData<P>,
+}
+
+impl<O: Write + Seek, P: FixedSizedPod> ChoicePodSerializer<O, P> {
+    /// Serialize a single value of the choice.
+    ///
+    /// Returns the amount of bytes written for this value.
+    pub fn serialize_value(&mut self, value: &P) -> Result<u64, GenError> {
+        let result = self
+            .serializer
+            .gen(|out| value.as_canonical_type().serialize_body(out));
+        self.written += 1;
+        result
+    }
+
+    /// Panics if `written` is not a valid number of values for `choice_type`.
+    fn check_arity(&self) {
+        if self.choice_type == spa_sys::SPA_CHOICE_None {
+            assert_eq!(
+                self.written, 1,
+                "A Choice::None must be serialized with exactly one value"
+            );
+        } else if self.choice_type == spa_sys::SPA_CHOICE_Range
+            || self.choice_type == spa_sys::SPA_CHOICE_Step
+        {
+            assert!(
+                self.written == 2 || self.written == 3,
+                "A Choice::Range or Choice::Step must be serialized with 2 or 3 values"
+            );
+        } else if self.choice_type == spa_sys::SPA_CHOICE_Enum
+            || self.choice_type == spa_sys::SPA_CHOICE_Flags
+        {
+            assert!(
+                self.written >= 1,
+                "A Choice::Enum or Choice::Flags must be serialized with at least one value"
+            );
+        }
+    }
+
+    /// Finish serializing the choice.
+    pub fn end(mut self) -> Result<SerializeSuccess<O>, GenError> {
+        self.check_arity();
+
+        let bytes_written = self.written * P::CanonicalType::SIZE;
+
+        let padding = if bytes_written % 8 == 0 {
+            0
+        } else {
+            8 - (bytes_written as usize % 8)
+        };
+
+        // Add padding to the pod.
+        let pad_bytes = self.serializer.gen(PodSerializer::padding(padding))?;
+
+        // Size of the body is the choice body (8 bytes) plus the child header (8 bytes)
+        // plus the size of the written values.
+        let body_size = 16 + bytes_written as usize;
+
+        if self.known_size.is_none() {
+            self.serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::Start(self.header_position))
+                .expect("Failed to seek to header position");
+
+            self.serializer
+                .gen(PodSerializer::header(body_size, spa_sys::SPA_TYPE_Choice))?;
+
+            self.serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::End(0))
+                .expect("Failed to seek to end");
+        }
+
+        self.serializer.compound_end(body_size as u64);
+
+        Ok(SerializeSuccess {
+            serializer: self.serializer,
+            len: 8 + body_size as u64 + pad_bytes,
+        })
+    }
+}
+
+/// This struct handles serializing `Sequence` pods.
+///
+/// It can be obtained by calling [`PodSerializer::serialize_sequence`].
+///
+/// Its [`serialize_control`](`Self::serialize_control`) method can be repeatedly called to
+/// serialize one control point each. To finalize the sequence, its [`end`](`Self::end`) method
+/// must be called.
+pub struct SequencePodSerializer<O: Write + Seek> {
+    /// The serializer is saved in an option, but can be expected to always be a `Some`
+    /// when `serialize_control()` or `end()` is called.
+    serializer: Option<PodSerializer<O>>,
+    /// The position to seek to when modifying header.
+    header_position: u64,
+    /// Bytes written to the sequence body so far. Starts at 8 to account for the
+    /// `unit`/`pad` pair written by `serialize_sequence`.
+    written: usize,
+    /// `Some` if this sequence's header was already written with its final size up front, as
+    /// part of [`serialize_to_writer`]'s replay pass, in which case `end()`
+    /// must not seek back to patch it.
+    known_size: Option<u64>,
+}
+
+impl<O: Write + Seek> SequencePodSerializer<O> {
+    /// Serialize a single control point of the sequence.
+    ///
+    /// Returns the amount of bytes written for this control point.
+    pub fn serialize_control<P>(
+        &mut self,
+        offset: u32,
+        control_type: u32,
+        value: &P,
+    ) -> Result<u64, GenError>
+    where
+        P: PodSerialize + ?Sized,
+    {
+        let header_len = self
+            .serializer
+            .as_mut()
+            .expect("SequencePodSerializer does not contain a serializer")
+            .gen(pair(ne_u32(offset), ne_u32(control_type)))?;
+
+        let success = value.serialize(
+            self.serializer
+                .take()
+                .expect("SequencePodSerializer does not contain a serializer"),
+        )?;
+
+        self.written += header_len as usize + success.len as usize;
+        self.serializer = Some(success.serializer);
+        Ok(header_len + success.len)
+    }
+
+    /// Finish serialization of the pod.
+    pub fn end(self) -> Result<SerializeSuccess<O>, GenError> {
+        let mut serializer = self
+            .serializer
+            .expect("SequencePodSerializer does not contain a serializer");
+
+        let padding = if self.written % 8 == 0 {
+            0
+        } else {
+            8 - (self.written % 8)
+        };
+
+        // Add padding to the pod.
+        let pad_bytes = serializer.gen(PodSerializer::padding(padding))?;
+
+        if self.known_size.is_none() {
+            // Seek to header position, write header with updated size, seek back.
+            serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::Start(self.header_position))
+                .expect("Failed to seek to header position");
+
+            serializer.gen(PodSerializer::header(
+                self.written,
+                spa_sys::SPA_TYPE_Sequence,
+            ))?;
+
+            serializer
+                .out
+                .as_mut()
+                .expect("Serializer does not contain a writer")
+                .seek(SeekFrom::End(0))
+                .expect("Failed to seek to end");
+        }
+
+        serializer.compound_end(self.written as u64);
+
+        Ok(SerializeSuccess {
+            serializer,
+            len: self.written as u64 + 8 + pad_bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    struct ObjectFixture;
+
+    impl PodSerialize for ObjectFixture {
+        fn serialize<O: Write + Seek>(
+            &self,
+            serializer: PodSerializer<O>,
+        ) -> Result<SerializeSuccess<O>, GenError> {
+            let mut object = serializer.serialize_object(1, 2)?;
+            object.serialize_property(1, 0, &42i32)?;
+            object.serialize_property(2, 1, &(-7i32))?;
+            object.end()
+        }
+    }
+
+    #[test]
+    fn object_byte_layout() {
+        let (cursor, len) =
+            PodSerializer::serialize(Cursor::new(Vec::new()), &ObjectFixture).unwrap();
+        let buf = cursor.into_inner();
+
+        let int_pod = |v: i32| {
+            let mut bytes = Vec::new();
+            bytes.extend(4u32.to_ne_bytes());
+            bytes.extend(spa_sys::SPA_TYPE_Int.to_ne_bytes());
+            bytes.extend(v.to_ne_bytes());
+            bytes.extend([0u8; 4]);
+            bytes
+        };
+
+        let mut expected = Vec::new();
+        expected.extend(56u32.to_ne_bytes()); // body size
+        expected.extend(spa_sys::SPA_TYPE_Object.to_ne_bytes());
+        expected.extend(1u32.to_ne_bytes()); // object_type
+        expected.extend(2u32.to_ne_bytes()); // object_id
+        expected.extend(1u32.to_ne_bytes()); // key
+        expected.extend(0u32.to_ne_bytes()); // flags
+        expected.extend(int_pod(42));
+        expected.extend(2u32.to_ne_bytes()); // key
+        expected.extend(1u32.to_ne_bytes()); // flags
+        expected.extend(int_pod(-7));
+
+        assert_eq!(len, expected.len() as u64);
+        assert_eq!(buf, expected);
+    }
+
+    struct ChoiceFixture;
+
+    impl PodSerialize for ChoiceFixture {
+        fn serialize<O: Write + Seek>(
+            &self,
+            serializer: PodSerializer<O>,
+        ) -> Result<SerializeSuccess<O>, GenError> {
+            let mut choice = serializer.serialize_choice::<i32>(spa_sys::SPA_CHOICE_Range, 0)?;
+            choice.serialize_value(&1)?;
+            choice.serialize_value(&2)?;
+            choice.serialize_value(&3)?;
+            choice.end()
+        }
+    }
+
+    #[test]
+    fn choice_byte_layout() {
+        let (cursor, len) =
+            PodSerializer::serialize(Cursor::new(Vec::new()), &ChoiceFixture).unwrap();
+        let buf = cursor.into_inner();
+
+        let mut expected = Vec::new();
+        expected.extend(28u32.to_ne_bytes()); // body size: 16 + 3 values * 4 bytes
+        expected.extend(spa_sys::SPA_TYPE_Choice.to_ne_bytes());
+        expected.extend(spa_sys::SPA_CHOICE_Range.to_ne_bytes());
+        expected.extend(0u32.to_ne_bytes()); // flags
+        expected.extend(4u32.to_ne_bytes()); // child size
+        expected.extend(spa_sys::SPA_TYPE_Int.to_ne_bytes()); // child type
+        expected.extend(1i32.to_ne_bytes());
+        expected.extend(2i32.to_ne_bytes());
+        expected.extend(3i32.to_ne_bytes());
+        expected.extend([0u8; 4]); // padding to 8-byte alignment
+
+        assert_eq!(len, expected.len() as u64);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Choice::Range or Choice::Step must be serialized with 2 or 3 values")]
+    fn choice_rejects_invalid_arity() {
+        struct InvalidChoiceFixture;
+
+        impl PodSerialize for InvalidChoiceFixture {
+            fn serialize<O: Write + Seek>(
+                &self,
+                serializer: PodSerializer<O>,
+            ) -> Result<SerializeSuccess<O>, GenError> {
+                let mut choice =
+                    serializer.serialize_choice::<i32>(spa_sys::SPA_CHOICE_Range, 0)?;
+                choice.serialize_value(&1)?;
+                choice.end()
+            }
+        }
+
+        PodSerializer::serialize(Cursor::new(Vec::new()), &InvalidChoiceFixture).unwrap();
+    }
+
+    struct InnerStructFixture;
+
+    impl PodSerialize for InnerStructFixture {
+        fn serialize<O: Write + Seek>(
+            &self,
+            serializer: PodSerializer<O>,
+        ) -> Result<SerializeSuccess<O>, GenError> {
+            let mut s = serializer.serialize_struct()?;
+            s.serialize_field(&10i32)?;
+            s.serialize_field(&20i32)?;
+            s.end()
+        }
+    }
+
+    /// A `Struct` nested inside a `Struct`, so the two-pass size-precompute/replay machinery
+    /// in [`serialize_to_writer`] has to resolve a compound pod nested inside another one,
+    /// not just a single flat one.
+    struct NestedStructFixture;
+
+    impl PodSerialize for NestedStructFixture {
+        fn serialize<O: Write + Seek>(
+            &self,
+            serializer: PodSerializer<O>,
+        ) -> Result<SerializeSuccess<O>, GenError> {
+            let mut s = serializer.serialize_struct()?;
+            s.serialize_field(&1i32)?;
+            s.serialize_field(&InnerStructFixture)?;
+            s.serialize_field(&2i32)?;
+            s.end()
+        }
+    }
+
+    #[test]
+    fn serialize_to_writer_matches_seeking_serialize() {
+        let (cursor, seek_len) =
+            PodSerializer::serialize(Cursor::new(Vec::new()), &NestedStructFixture).unwrap();
+        let seek_buf = cursor.into_inner();
+
+        let (stream_buf, stream_len) =
+            serialize_to_writer(Vec::new(), &NestedStructFixture).unwrap();
+
+        assert_eq!(seek_len, stream_len);
+        assert_eq!(seek_buf, stream_buf);
+    }
+
+    struct SequenceFixture;
+
+    impl PodSerialize for SequenceFixture {
+        fn serialize<O: Write + Seek>(
+            &self,
+            serializer: PodSerializer<O>,
+        ) -> Result<SerializeSuccess<O>, GenError> {
+            let mut sequence = serializer.serialize_sequence(0)?;
+            sequence.serialize_control(0, 1, &10i32)?;
+            sequence.serialize_control(5, 2, &20i32)?;
+            sequence.end()
+        }
+    }
+
+    #[test]
+    fn sequence_byte_layout() {
+        let (cursor, len) =
+            PodSerializer::serialize(Cursor::new(Vec::new()), &SequenceFixture).unwrap();
+        let buf = cursor.into_inner();
+
+        let int_pod = |v: i32| {
+            let mut bytes = Vec::new();
+            bytes.extend(4u32.to_ne_bytes());
+            bytes.extend(spa_sys::SPA_TYPE_Int.to_ne_bytes());
+            bytes.extend(v.to_ne_bytes());
+            bytes.extend([0u8; 4]);
+            bytes
+        };
+
+        let mut expected = Vec::new();
+        expected.extend(56u32.to_ne_bytes()); // body size
+        expected.extend(spa_sys::SPA_TYPE_Sequence.to_ne_bytes());
+        expected.extend(0u32.to_ne_bytes()); // unit
+        expected.extend(0u32.to_ne_bytes()); // pad
+        expected.extend(0u32.to_ne_bytes()); // offset
+        expected.extend(1u32.to_ne_bytes()); // control type
+        expected.extend(int_pod(10));
+        expected.extend(5u32.to_ne_bytes()); // offset
+        expected.extend(2u32.to_ne_bytes()); // control type
+        expected.extend(int_pod(20));
+        // Both controls already end 8-byte aligned, so no trailing padding is expected.
+
+        assert_eq!(len, expected.len() as u64);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn serialize_into_slice_matches_seeking_serialize_in_oversized_buffer() {
+        let (cursor, seek_len) =
+            PodSerializer::serialize(Cursor::new(Vec::new()), &NestedStructFixture).unwrap();
+        let seek_buf = cursor.into_inner();
+
+        // Deliberately oversized, so a correct `SliceWriter` must report the pod's own
+        // length instead of the whole buffer's capacity, and the trailing field after the
+        // nested struct must still land right after it instead of at `buf.len()`.
+        let mut slice_buf = [0xffu8; 256];
+        let written =
+            PodSerializer::serialize_into_slice(&mut slice_buf, &NestedStructFixture).unwrap();
+
+        assert_eq!(written as u64, seek_len);
+        assert_eq!(&slice_buf[..written], seek_buf.as_slice());
+    }
+}
@@ -0,0 +1,422 @@
+//! A [`serde::Serializer`] adapter over [`PodSerializer`], so any `#[derive(Serialize)]`
+//! type can be written straight to a raw POD without a hand-written [`PodSerialize`] impl.
+//!
+//! Use [`to_pod`] to serialize a top-level value.
+//!
+//! The serde data model is mapped onto POD types the same way the other primitive
+//! [`PodSerialize`] impls in this crate do: `bool` → `Bool`, `i32` → `Int`, `i64`/`isize` →
+//! `Long`, `f32` → `Float`, `f64` → `Double`, `str` → `String`, bytes → `Bytes`. The narrower
+//! integer types are widened to the nearest type the POD format can represent. Sequences and
+//! tuples are written as a `Struct` pod (they may hold elements of different types), and maps
+//! and structs are written as a `Struct` pod of alternating key/value fields, with a struct's
+//! field names used as its "keys".
+//!
+//! [`PodSerialize`]: super::PodSerialize
+
+use std::convert::TryInto;
+use std::io::{Seek, Write};
+
+use serde::ser;
+use serde::Serialize;
+
+use super::{GenError, PodSerializer, SerializeSuccess, StructPodSerializer};
+
+/// Error returned when a value cannot be serialized into a POD.
+#[derive(Debug)]
+pub enum Error {
+    /// Writing the pod itself failed.
+    Gen(GenError),
+    /// The value could not be mapped onto the POD type system, or the `Serialize` impl
+    /// raised a custom error.
+    Message(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Gen(e) => write!(f, "{:?}", e),
+            Error::Message(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl From<GenError> for Error {
+    fn from(e: GenError) -> Self {
+        Error::Gen(e)
+    }
+}
+
+/// Serialize `value` into `out`, returning the writer back alongside the number of bytes
+/// written, the same way [`PodSerializer::serialize`] does.
+pub fn to_pod<O, T>(out: O, value: &T) -> Result<(O, u64), Error>
+where
+    O: Write + Seek,
+    T: ?Sized + Serialize,
+{
+    let serializer = Serializer {
+        serializer: PodSerializer {
+            out: Some(out),
+            size_tracker: None,
+        },
+    };
+    let success = value.serialize(serializer)?;
+    Ok((
+        success
+            .serializer
+            .out
+            .expect("PodSerializer does not contain a writer"),
+        success.len,
+    ))
+}
+
+/// Drives a single field of a [`StructPodSerializer`] using a `serde::Serialize` value
+/// instead of a [`PodSerialize`](super::PodSerialize) one.
+fn serialize_struct_field<O, T>(
+    struct_ser: &mut StructPodSerializer<O>,
+    value: &T,
+) -> Result<u64, Error>
+where
+    O: Write + Seek,
+    T: ?Sized + Serialize,
+{
+    let inner = struct_ser
+        .serializer
+        .take()
+        .expect("StructPodSerializer does not contain a serializer");
+    let success = value.serialize(Serializer { serializer: inner })?;
+    struct_ser.written += success.len as usize;
+    struct_ser.serializer = Some(success.serializer);
+    Ok(success.len)
+}
+
+/// A [`serde::Serializer`] that writes values directly into a raw SPA pod.
+///
+/// This is constructed internally by [`to_pod`]; implementors of `Serialize` never need to
+/// name this type themselves.
+pub struct Serializer<O: Write + Seek> {
+    serializer: PodSerializer<O>,
+}
+
+impl<O: Write + Seek> ser::Serializer for Serializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    type SerializeSeq = SeqSerializer<O>;
+    type SerializeTuple = SeqSerializer<O>;
+    type SerializeTupleStruct = SeqSerializer<O>;
+    type SerializeTupleVariant = SeqSerializer<O>;
+    type SerializeMap = MapSerializer<O>;
+    type SerializeStruct = MapSerializer<O>;
+    type SerializeStructVariant = MapSerializer<O>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(self.serializer.serialized_fixed_sized_pod(&v)?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(self.serializer.serialized_fixed_sized_pod(&v)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(self.serializer.serialized_fixed_sized_pod(&v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        let v: i64 = v
+            .try_into()
+            .map_err(|_| Error::Message(format!("{} does not fit into a Long pod", v)))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(self.serializer.serialized_fixed_sized_pod(&v)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(self.serializer.serialized_fixed_sized_pod(&v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        let mut buf = [0u8; 4];
+        self.serialize_str(v.encode_utf8(&mut buf))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(self.serializer.serialize_string(v)?)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(self.serializer.serialize_bytes(v)?)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self
+            .serializer
+            .write_pod(0, spa_sys::SPA_TYPE_None, |out| Ok(out))?)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut inner = self.serializer.serialize_struct()?;
+        serialize_struct_field(&mut inner, variant)?;
+        serialize_struct_field(&mut inner, value)?;
+        Ok(inner.end()?)
+    }
+
+    // Sequences always go through `serialize_struct()` rather than the fixed-size `Array`
+    // pod. Taking the `Array` fast path requires knowing every element is the same
+    // `FixedSizedPod` type before writing the header, but a `Serializer` only learns an
+    // element's type as it is handed to `serialize_element`, one at a time, with no look-ahead
+    // to confirm the rest of the sequence matches. Buffering elements to check that up front
+    // would defeat the point of driving the `PodSerialize` impls directly, so this always
+    // emits a (heterogeneous-capable) Struct pod instead, even for genuinely fixed-size
+    // sequences.
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqSerializer {
+            inner: self.serializer.serialize_struct()?,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        let mut inner = self.serializer.serialize_struct()?;
+        serialize_struct_field(&mut inner, variant)?;
+        Ok(SeqSerializer { inner })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapSerializer {
+            inner: self.serializer.serialize_struct()?,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        let mut inner = self.serializer.serialize_struct()?;
+        serialize_struct_field(&mut inner, variant)?;
+        Ok(MapSerializer { inner })
+    }
+}
+
+/// Backs [`Serializer`]'s `SerializeSeq`/`SerializeTuple`/`SerializeTupleStruct`/
+/// `SerializeTupleVariant` implementations. Writes each element as one field of a
+/// heterogeneous `Struct` pod, since a sequence's elements may not all share a POD type.
+pub struct SeqSerializer<O: Write + Seek> {
+    inner: StructPodSerializer<O>,
+}
+
+impl<O: Write + Seek> ser::SerializeSeq for SeqSerializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        serialize_struct_field(&mut self.inner, value)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.inner.end()?)
+    }
+}
+
+impl<O: Write + Seek> ser::SerializeTuple for SeqSerializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<O: Write + Seek> ser::SerializeTupleStruct for SeqSerializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<O: Write + Seek> ser::SerializeTupleVariant for SeqSerializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Backs [`Serializer`]'s `SerializeMap`/`SerializeStruct`/`SerializeStructVariant`
+/// implementations. Writes a `Struct` pod of alternating key/value fields; a struct's field
+/// names are written as the "keys".
+pub struct MapSerializer<O: Write + Seek> {
+    inner: StructPodSerializer<O>,
+}
+
+impl<O: Write + Seek> ser::SerializeMap for MapSerializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        serialize_struct_field(&mut self.inner, key)?;
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        serialize_struct_field(&mut self.inner, value)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.inner.end()?)
+    }
+}
+
+impl<O: Write + Seek> ser::SerializeStruct for MapSerializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        serialize_struct_field(&mut self.inner, key)?;
+        serialize_struct_field(&mut self.inner, value)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl<O: Write + Seek> ser::SerializeStructVariant for MapSerializer<O> {
+    type Ok = SerializeSuccess<O>;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeMap::end(self)
+    }
+}
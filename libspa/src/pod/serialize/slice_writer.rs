@@ -0,0 +1,103 @@
+//! A [`Write`]/[`Seek`] implementation over a fixed, caller-provided buffer.
+//!
+//! [`PodSerializer::serialize`](`super::PodSerializer::serialize`) normally writes into
+//! whatever seekable sink the caller hands it, such as a `Cursor<Vec<u8>>`. That works well
+//! when allocation is acceptable, but real-time audio processing paths often can't allocate
+//! at all, and the POD being produced usually has a known upper bound on its size anyway.
+//! [`SliceWriter`] lets such callers serialize straight into a `&mut [u8]` they already own.
+//!
+//! A [`std::io::Cursor`] around a `&mut [u8]` already implements [`Write`] and [`Seek`], but
+//! it resolves `SeekFrom::End` against the length of the whole slice. Every compound pod
+//! driven through [`PodSerializer`](`super::PodSerializer`) seeks to `SeekFrom::End(0)` after
+//! patching its header, so with a buffer bigger than the pod, the rest of the pod would land
+//! at the end of the buffer instead of right after what was written. [`SliceWriter`] resolves
+//! `SeekFrom::End` against the number of bytes written so far instead.
+//!
+//! Like `Cursor`, [`SliceWriter`] writes fewer bytes than it was given once the buffer fills
+//! up. That short write is exactly what `cookie_factory`'s own generators already watch for:
+//! they turn it into a [`GenError::BufferTooSmall`] instead of returning a truncated,
+//! falsely-`Ok` result.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+use super::{GenError, PodSerialize, PodSerializer};
+
+/// Wraps a `&mut [u8]` so it can be used as the output of a [`PodSerializer`] without
+/// allocating, reporting [`GenError::BufferTooSmall`] instead of truncating if the pod does
+/// not fit.
+pub struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+    /// The high-water mark of `pos`, i.e. the number of bytes actually written so far.
+    ///
+    /// `end()` on every compound pod finishes by seeking to `SeekFrom::End(0)`, which must
+    /// land back at this mark rather than at `buf.len()`, or a buffer bigger than the pod
+    /// would make the seek jump past what was written, as would `bytes_written()` if it
+    /// reported `pos` instead.
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    /// Wrap `buf` for serializing a pod into it.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// The number of bytes written into the buffer so far.
+    pub fn bytes_written(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a> Write for SliceWriter<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let remaining = self.buf.len() - self.pos;
+        let n = data.len().min(remaining);
+        self.buf[self.pos..self.pos + n].copy_from_slice(&data[..n]);
+        self.pos += n;
+        self.len = self.len.max(self.pos);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> Seek for SliceWriter<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(n) => n as i64,
+            SeekFrom::End(n) => self.len as i64 + n,
+            SeekFrom::Current(n) => self.pos as i64 + n,
+        };
+
+        if new_pos < 0 || new_pos as usize > self.buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek position is out of the slice's bounds",
+            ));
+        }
+
+        self.pos = new_pos as usize;
+        Ok(self.pos as u64)
+    }
+}
+
+impl<'a> PodSerializer<SliceWriter<'a>> {
+    /// Serialize `pod` into `buf` without allocating, returning the number of bytes written.
+    ///
+    /// Fails with [`GenError::BufferTooSmall`] rather than panicking or silently truncating
+    /// if `pod` does not fit in `buf`.
+    pub fn serialize_into_slice<P>(buf: &'a mut [u8], pod: &P) -> Result<usize, GenError>
+    where
+        P: PodSerialize + ?Sized,
+    {
+        let (writer, _) = Self::serialize(SliceWriter::new(buf), pod)?;
+        Ok(writer.bytes_written())
+    }
+}